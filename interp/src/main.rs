@@ -1,6 +1,6 @@
-use std::{fs::{self, File}, process::{exit, id}, sync::*, thread, time::{Duration, Instant}};
+use std::{collections::{HashMap, HashSet}, env, fs::{self, File}, io::{self, BufRead, Write}, process::{exit, id}, sync::*, thread, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
 
-use macroquad::{audio::{Sound, play_sound}, prelude::*, rand::{self, rand}};
+use macroquad::{audio::{Sound, play_sound}, prelude::*};
 
 const FILE_NOT_FOUND: i32 = 1;
 
@@ -69,6 +69,148 @@ impl Stack {
     }
 }
 
+// Minimal ChaCha-style counter-based PRNG: 10 double-rounds over a 16-word
+// state seeded from a u64, so runs are byte-for-byte reproducible when the
+// same seed is given.
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]); state[d] ^= state[a]; state[d] = state[d].rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]); state[b] ^= state[c]; state[b] = state[b].rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]); state[d] ^= state[a]; state[d] = state[d].rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]); state[b] ^= state[c]; state[b] = state[b].rotate_left(7);
+}
+
+struct Rng {
+    state:  [u32; 16], // constants + key + counter/nonce
+    block:  [u32; 16], // current keystream block
+    pos:    usize, // next unread word in block
+}
+
+impl Rng {
+    pub fn seeded(seed: u64) -> Self {
+        let mut state = [0u32; 16];
+        state[0] = 0x61707865;
+        state[1] = 0x3320646e;
+        state[2] = 0x79622d32;
+        state[3] = 0x6b206574;
+        for i in 0..8 {
+            state[4 + i] = seed.rotate_left((i as u32) * 11).wrapping_add(i as u64) as u32;
+        }
+        state[12] = 0; // block counter
+        state[13] = 0;
+        state[14] = (seed & 0xFFFF_FFFF) as u32;
+        state[15] = (seed >> 32) as u32;
+
+        return Self { state, block: [0; 16], pos: 16 };
+    }
+
+    pub fn from_entropy() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+        let seed = nanos ^ ((id() as u64) << 32);
+        return Self::seeded(seed);
+    }
+
+    fn refill(&mut self) {
+        let mut working = self.state;
+        for _ in 0..10 {
+            quarter_round(&mut working, 0, 4, 8, 12);
+            quarter_round(&mut working, 1, 5, 9, 13);
+            quarter_round(&mut working, 2, 6, 10, 14);
+            quarter_round(&mut working, 3, 7, 11, 15);
+            quarter_round(&mut working, 0, 5, 10, 15);
+            quarter_round(&mut working, 1, 6, 11, 12);
+            quarter_round(&mut working, 2, 7, 8, 13);
+            quarter_round(&mut working, 3, 4, 9, 14);
+        }
+
+        for i in 0..16 {
+            self.block[i] = working[i].wrapping_add(self.state[i]);
+        }
+
+        self.state[12] = self.state[12].wrapping_add(1);
+        if self.state[12] == 0 {
+            self.state[13] = self.state[13].wrapping_add(1);
+        }
+        self.pos = 0;
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        if self.pos >= 16 {
+            self.refill();
+        }
+        let v = self.block[self.pos];
+        self.pos += 1;
+        return v;
+    }
+}
+
+// Toggles for the handful of CHIP-8 opcodes whose behavior differs between
+// the original COSMAC VIP interpreter and its later CHIP-48/SUPER-CHIP
+// descendants. Different ROMs assume different answers here.
+#[derive(Clone, Copy)]
+struct Quirks {
+    shift_uses_vy:              bool, // 8xy6/8xyE read from Vy instead of shifting Vx in place
+    load_store_increments_idx:  bool, // Fx55/Fx65 advance I by x + 1 afterwards
+    jump_offset_uses_vx:        bool, // Bnnn/Bxnn adds Vx (CHIP-48) instead of V0
+    clip_sprites:               bool, // Dxyn clips at the screen edge instead of wrapping
+    cycles_per_frame:           u32, // instructions executed between each redraw
+}
+
+impl Quirks {
+    pub fn cosmac_vip() -> Self {
+        return Self {
+            shift_uses_vy: true,
+            load_store_increments_idx: true,
+            jump_offset_uses_vx: false,
+            clip_sprites: true,
+            cycles_per_frame: 11,
+        };
+    }
+
+    pub fn chip48() -> Self {
+        return Self {
+            shift_uses_vy: false,
+            load_store_increments_idx: false,
+            jump_offset_uses_vx: true,
+            clip_sprites: true,
+            cycles_per_frame: 15,
+        };
+    }
+
+    pub fn super_chip() -> Self {
+        return Self {
+            shift_uses_vy: false,
+            load_store_increments_idx: false,
+            jump_offset_uses_vx: true,
+            clip_sprites: true,
+            cycles_per_frame: 30,
+        };
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        return match name {
+            "vip" | "cosmac-vip" => Some(Self::cosmac_vip()),
+            "chip48" | "chip-48" => Some(Self::chip48()),
+            "schip" | "super-chip" => Some(Self::super_chip()),
+            _ => None,
+        };
+    }
+}
+
+impl Default for Quirks {
+    // Used when no --variant flag is given: shifts work on Vx in place,
+    // Fx55/Fx65 leave I unchanged, Bnnn adds V0, sprites wrap at the screen
+    // edges, and one instruction runs per redraw.
+    fn default() -> Self {
+        return Self {
+            shift_uses_vy: false,
+            load_store_increments_idx: false,
+            jump_offset_uses_vx: false,
+            clip_sprites: false,
+            cycles_per_frame: 1,
+        };
+    }
+}
+
 fn init_font() -> [u8; 4096] {
     let mut mem = [0; 4096];
     mem[..FONT.len()].copy_from_slice(&FONT);
@@ -84,22 +226,42 @@ struct Emulator {
     delay:      Arc<Mutex<u8>>, // delay timer
     sound:      Arc<Mutex<u8>>, // sound timer
     reg:        [u8; 16], // general purpose registers
+    block_cache: HashMap<u16, Vec<Instruction>>, // decoded basic blocks, keyed by start pc
+    active_block: Option<(u16, usize)>, // (start, len) of the block pc is currently stepping through
+    rng:        Rng, // source of randomness for Cxkk
+    quirks:     Quirks, // selected variant behavior
 }
 
 impl Emulator {
     pub const PROGRAM_START: usize = 0x200;
 
-    pub fn init() -> Self {
-        return Self { 
-            mem: init_font(), 
-            disp: [0; 256], 
-            pc: Emulator::PROGRAM_START as u16, 
-            idx: 0, 
-            stack: Stack::new(), 
-            delay: Arc::new(Mutex::new(0)), 
-            sound: Arc::new(Mutex::new(0)), 
-            reg: [0; 16] 
+    pub fn init_with_quirks(quirks: Quirks, seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => Rng::seeded(seed),
+            None => Rng::from_entropy(),
         };
+        return Self::build(rng, quirks);
+    }
+
+    fn build(rng: Rng, quirks: Quirks) -> Self {
+        return Self {
+            mem: init_font(),
+            disp: [0; 256],
+            pc: Emulator::PROGRAM_START as u16,
+            idx: 0,
+            stack: Stack::new(),
+            delay: Arc::new(Mutex::new(0)),
+            sound: Arc::new(Mutex::new(0)),
+            reg: [0; 16],
+            block_cache: HashMap::new(),
+            active_block: None,
+            rng,
+            quirks,
+        };
+    }
+
+    pub fn next_rand(&mut self) -> u8 {
+        return (self.rng.next_u32() & 0xff) as u8;
     }
 
     pub fn getWord(&self, addr: u16) -> u8 {
@@ -108,6 +270,7 @@ impl Emulator {
 
     pub fn putWord(&mut self, addr: u16, value: u8) {
         self.mem[addr as usize] = value;
+        self.invalidate_blocks(addr, 1);
     }
 
     pub fn getDWord(&self, addr: u16) -> u16 {
@@ -121,10 +284,22 @@ impl Emulator {
     pub fn putDWord(&mut self, addr: u16, value: u16) {
         let lsb = (value & 0xff) as u8;
         let msb = ((value >> 8) & 0xff) as u8;
-        
+
         let addr = addr as usize;
         self.mem[addr] = msb;
         self.mem[addr + 1] = lsb;
+        self.invalidate_blocks(addr as u16, 2);
+    }
+
+    // CHIP-8 ROMs can self-modify, so any write has to evict cached blocks
+    // whose decoded [start, end) span overlaps the write.
+    fn invalidate_blocks(&mut self, addr: u16, len: u16) {
+        let write_start = addr;
+        let write_end = addr + len;
+        self.block_cache.retain(|&start, block| {
+            let end = start + (block.len() as u16) * 2;
+            !(write_start < end && start < write_end)
+        });
     }
 
     pub fn loadROM(&mut self, file_name: &str) {
@@ -200,7 +375,143 @@ fn tick(delay: Arc<Mutex<u8>>, sound: Arc<Mutex<u8>>) {
     }
 }
 
+struct Debugger {
+    breakpoints:    HashSet<u16>, // addresses that pause execution when hit
+    repeat:         u32, // cycles left to auto-run before the next stop is allowed
+    stepping:       bool, // stop once repeat hits 0, regardless of breakpoints
+    trace_only:     bool, // print every instruction before it runs; breakpoints and stepping still stop
+    last_command:   Option<String>, // re-run on a blank line
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        return Self {
+            breakpoints: HashSet::new(),
+            repeat: 0,
+            stepping: false,
+            trace_only: false,
+            last_command: None,
+        };
+    }
+
+    pub fn should_break(&self, pc: u16) -> bool {
+        if self.repeat > 0 {
+            return false;
+        }
+        return self.stepping || self.breakpoints.contains(&pc);
+    }
+}
+
+fn dump_registers(emu: &Emulator) {
+    for i in 0..16 {
+        println!("V{0:X}: 0x{1:02X}", i, emu.reg[i]);
+    }
+    println!("I:  0x{:04X}", emu.idx);
+    println!("PC: 0x{:04X}", emu.pc);
+    println!("DT: 0x{:02X}", *emu.delay.lock().unwrap());
+    println!("ST: 0x{:02X}", *emu.sound.lock().unwrap());
+
+    print!("Stack:");
+    let mut frame = &emu.stack.top;
+    while let Some(f) = frame {
+        print!(" 0x{:04X}", f.value);
+        frame = &f.next;
+    }
+    println!();
+}
+
+fn dump_memory(emu: &Emulator, addr: u16, len: u16) {
+    let mut i: u16 = 0;
+    while i < len {
+        print!("{:04X}: ", addr.wrapping_add(i));
+        for j in 0..8 {
+            if i + j >= len { break; }
+            print!("{:02X} ", emu.getWord(addr.wrapping_add(i + j)));
+        }
+        println!();
+        i += 8;
+    }
+}
+
+fn parse_addr(s: &str) -> Option<u16> {
+    return u16::from_str_radix(s.trim_start_matches("0x"), 16).ok();
+}
+
+// Returns true if execution should resume, false if the debugger prompt should stay open.
+fn run_debugger_command(debugger: &mut Debugger, emu: &Emulator, cmd: &str) -> bool {
+    let mut parts = cmd.split_whitespace();
+    match parts.next() {
+        Some("step") => {
+            let n: u32 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(1);
+            debugger.repeat = n.saturating_sub(1);
+            debugger.stepping = true;
+            return true;
+        }
+        Some("continue") => {
+            debugger.repeat = 0;
+            debugger.stepping = false;
+            return true;
+        }
+        Some("break") => {
+            match parts.next().and_then(parse_addr) {
+                Some(addr) => {
+                    debugger.breakpoints.insert(addr);
+                    println!("Breakpoint set at 0x{:04X}", addr);
+                }
+                _ => println!("ERROR: usage: break <addr>")
+            }
+            return false;
+        }
+        Some("delete") => {
+            match parts.next().and_then(parse_addr) {
+                Some(addr) => {
+                    debugger.breakpoints.remove(&addr);
+                    println!("Breakpoint cleared at 0x{:04X}", addr);
+                }
+                _ => println!("ERROR: usage: delete <addr>")
+            }
+            return false;
+        }
+        Some("reg") => {
+            dump_registers(emu);
+            return false;
+        }
+        Some("mem") => {
+            match parts.next().and_then(parse_addr) {
+                Some(addr) => {
+                    let len: u16 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(16);
+                    dump_memory(emu, addr, len);
+                }
+                _ => println!("ERROR: usage: mem <addr> [len]")
+            }
+            return false;
+        }
+        Some("disasm") => {
+            match parts.next().and_then(parse_addr) {
+                Some(addr) => {
+                    let len: u16 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(16);
+                    disassemble(emu, addr, len);
+                }
+                _ => println!("ERROR: usage: disasm <addr> [len]")
+            }
+            return false;
+        }
+        Some("trace") => {
+            debugger.trace_only = !debugger.trace_only;
+            println!("Trace mode {0}", if debugger.trace_only {"enabled"} else {"disabled"});
+            return false;
+        }
+        Some(other) => {
+            println!("ERROR: Unknown debugger command: {0}", other);
+            return false;
+        }
+        None => return false,
+    }
+}
+
+#[derive(Clone, Copy)]
 struct Instruction {
+    raw:        u16,
     opcode:     u8,
     op1:        Option<u16>,
     op2:        Option<u16>,
@@ -240,10 +551,11 @@ macro_rules! PARSE_FORMAT_3 {
 impl Instruction {
     pub fn new(raw: u16) -> Instruction {
         let mut instr: Instruction = Instruction {
+            raw,
             opcode: ((raw >> 12) & 0xf) as u8,
-            op1: None, 
-            op2: None, 
-            op3: None 
+            op1: None,
+            op2: None,
+            op3: None
         };
         
         match instr.opcode {
@@ -256,14 +568,168 @@ impl Instruction {
     }
 }
 
-fn fetch(emu: &mut Emulator) -> u16 {
-    let instr = emu.getDWord(emu.pc);
-    emu.pc += 2;
-    return instr;
+impl core::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self.opcode {
+            0x0 => match self.op1 {
+                Some(0x0E0) => write!(f, "CLS"),
+                Some(0x0EE) => write!(f, "RET"),
+                Some(n) => write!(f, "SYS 0x{:03X}", n),
+                None => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0x1 => match self.op1 {
+                Some(n) => write!(f, "JP 0x{:03X}", n),
+                None => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0x2 => match self.op1 {
+                Some(n) => write!(f, "CALL 0x{:03X}", n),
+                None => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0x3 => match (self.op1, self.op2) {
+                (Some(x), Some(kk)) => write!(f, "SE V{:X}, 0x{:02X}", x, kk),
+                _ => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0x4 => match (self.op1, self.op2) {
+                (Some(x), Some(kk)) => write!(f, "SNE V{:X}, 0x{:02X}", x, kk),
+                _ => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0x5 => match (self.op1, self.op2) {
+                (Some(x), Some(y)) => write!(f, "SE V{:X}, V{:X}", x, y),
+                _ => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0x6 => match (self.op1, self.op2) {
+                (Some(x), Some(kk)) => write!(f, "LD V{:X}, 0x{:02X}", x, kk),
+                _ => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0x7 => match (self.op1, self.op2) {
+                (Some(x), Some(kk)) => write!(f, "ADD V{:X}, 0x{:02X}", x, kk),
+                _ => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0x8 => match (self.op1, self.op2, self.op3) {
+                (Some(x), Some(y), Some(0x0)) => write!(f, "LD V{:X}, V{:X}", x, y),
+                (Some(x), Some(y), Some(0x1)) => write!(f, "OR V{:X}, V{:X}", x, y),
+                (Some(x), Some(y), Some(0x2)) => write!(f, "AND V{:X}, V{:X}", x, y),
+                (Some(x), Some(y), Some(0x3)) => write!(f, "XOR V{:X}, V{:X}", x, y),
+                (Some(x), Some(y), Some(0x4)) => write!(f, "ADD V{:X}, V{:X}", x, y),
+                (Some(x), Some(y), Some(0x5)) => write!(f, "SUB V{:X}, V{:X}", x, y),
+                (Some(x), Some(y), Some(0x6)) => write!(f, "SHR V{:X}, V{:X}", x, y),
+                (Some(x), Some(y), Some(0x7)) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+                (Some(x), Some(y), Some(0xE)) => write!(f, "SHL V{:X}, V{:X}", x, y),
+                _ => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0x9 => match (self.op1, self.op2) {
+                (Some(x), Some(y)) => write!(f, "SNE V{:X}, V{:X}", x, y),
+                _ => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0xA => match self.op1 {
+                Some(n) => write!(f, "LD I, 0x{:03X}", n),
+                None => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0xB => match self.op1 {
+                Some(n) => write!(f, "JP V0, 0x{:03X}", n),
+                None => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0xC => match (self.op1, self.op2) {
+                (Some(x), Some(kk)) => write!(f, "RND V{:X}, 0x{:02X}", x, kk),
+                _ => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0xD => match (self.op1, self.op2, self.op3) {
+                (Some(x), Some(y), Some(n)) => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+                _ => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0xE => match (self.op1, self.op2) {
+                (Some(x), Some(0x9E)) => write!(f, "SKP V{:X}", x),
+                (Some(x), Some(0xA1)) => write!(f, "SKNP V{:X}", x),
+                _ => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            0xF => match (self.op1, self.op2) {
+                (Some(x), Some(0x07)) => write!(f, "LD V{:X}, DT", x),
+                (Some(x), Some(0x0A)) => write!(f, "LD V{:X}, K", x),
+                (Some(x), Some(0x15)) => write!(f, "LD DT, V{:X}", x),
+                (Some(x), Some(0x18)) => write!(f, "LD ST, V{:X}", x),
+                (Some(x), Some(0x1E)) => write!(f, "ADD I, V{:X}", x),
+                (Some(x), Some(0x29)) => write!(f, "LD F, V{:X}", x),
+                (Some(x), Some(0x33)) => write!(f, "LD B, V{:X}", x),
+                (Some(x), Some(0x55)) => write!(f, "LD [I], V{:X}", x),
+                (Some(x), Some(0x65)) => write!(f, "LD V{:X}, [I]", x),
+                _ => write!(f, "DB 0x{:04X}", self.raw),
+            }
+            _ => write!(f, "DB 0x{:04X}", self.raw),
+        }
+    }
+}
+
+fn disassemble(emu: &Emulator, start: u16, len: u16) {
+    let mut addr = start;
+    let end = start.saturating_add(len);
+    while addr < end {
+        let raw = emu.getDWord(addr);
+        let instr = Instruction::new(raw);
+        println!("{:04X}: {:04X}  {}", addr, raw, instr);
+        addr += 2;
+    }
 }
 
-fn decode(dword: u16) -> Instruction {
-    return Instruction::new(dword);
+// Whether this opcode can redirect the pc, which means it has to be the
+// last instruction decoded into a basic block.
+fn ends_block(instr: &Instruction) -> bool {
+    match instr.opcode {
+        0x0 => matches!(instr.op1, Some(0x0EE)),
+        0x1 | 0x2 | 0xB => true,
+        0x3 | 0x4 | 0x5 | 0x9 => true,
+        0xE => matches!(instr.op2, Some(0x9E) | Some(0xA1)),
+        0xF => matches!(instr.op2, Some(0x0A)),
+        _ => false,
+    }
+}
+
+fn decode_block(emu: &Emulator, start: u16) -> Vec<Instruction> {
+    let mut block = Vec::new();
+    let mut addr = start;
+    loop {
+        // Nothing past here to decode (e.g. execution has run off the end of
+        // the loaded ROM into the zero-filled tail of memory, which never
+        // hits a control-flow opcode) — stop before getDWord reads out of bounds.
+        if (addr as usize) + 2 > emu.mem.len() {
+            break;
+        }
+
+        let instr = Instruction::new(emu.getDWord(addr));
+        let last = ends_block(&instr);
+        block.push(instr);
+        if last {
+            break;
+        }
+        addr += 2;
+    }
+    return block;
+}
+
+// Executes exactly one instruction at emu.pc, so callers (the debugger, the
+// cycles-per-frame budget) see every instruction rather than a whole block
+// at once. The block straight-line run starting at pc is decoded and cached
+// the first time it's reached; while pc is still stepping through that same
+// cached run, lookups are by cursor instead of re-decoding.
+fn execute_block(emu: &mut Emulator) {
+    let pc = emu.pc;
+
+    let start = match emu.active_block {
+        Some((start, len)) if pc >= start && pc < start + (len as u16) * 2 && emu.block_cache.contains_key(&start) => start,
+        _ => {
+            if !emu.block_cache.contains_key(&pc) {
+                let block = decode_block(emu, pc);
+                emu.block_cache.insert(pc, block);
+            }
+            emu.active_block = Some((pc, emu.block_cache.get(&pc).unwrap().len()));
+            pc
+        }
+    };
+
+    let idx = ((pc - start) / 2) as usize;
+    let instr = emu.block_cache.get(&start).unwrap()[idx];
+
+    emu.pc += 2;
+    execute(emu, instr);
 }
 
 fn execute(emu: &mut Emulator, instr: Instruction) {
@@ -380,10 +846,11 @@ fn execute(emu: &mut Emulator, instr: Instruction) {
                     emu.reg[0xf] = if emu.reg[x] > emu.reg[y] {1} else {0};
                     emu.reg[x] -= emu.reg[y];
                 }
-                (Some(x), Some(_), Some(0x6)) => {
+                (Some(x), Some(y), Some(0x6)) => {
                     let x = x as usize;
-                    emu.reg[0xf] = if emu.reg[x] & 0x1 == 1 {1} else {0};
-                    emu.reg[x] /= 2;
+                    let src = if emu.quirks.shift_uses_vy { emu.reg[y as usize] } else { emu.reg[x] };
+                    emu.reg[0xf] = src & 0x1;
+                    emu.reg[x] = src >> 1;
                 }
                 (Some(x), Some(y), Some(0x7)) => {
                     let x = x as usize;
@@ -392,10 +859,11 @@ fn execute(emu: &mut Emulator, instr: Instruction) {
                     emu.reg[0xf] = if emu.reg[x] < emu.reg[y] {1} else {0};
                     emu.reg[x] = emu.reg[y] - emu.reg[x];
                 }
-                (Some(x), Some(_), Some(0xE)) => {
+                (Some(x), Some(y), Some(0xE)) => {
                     let x = x as usize;
-                    emu.reg[0xf] = if emu.reg[x] >> 7 & 0x1 == 1 {1} else {0};
-                    emu.reg[x] *= 2;
+                    let src = if emu.quirks.shift_uses_vy { emu.reg[y as usize] } else { emu.reg[x] };
+                    emu.reg[0xf] = (src >> 7) & 0x1;
+                    emu.reg[x] = src << 1;
                 }
                 _ => println!("ERROR: Unknown operand for opcode: {:x}", instr.opcode)
             }
@@ -421,7 +889,13 @@ fn execute(emu: &mut Emulator, instr: Instruction) {
         0xB => {
             match instr.op1 {
                 Some(n) => {
-                    emu.pc = (emu.reg[0] as u16) + n;
+                    let offset = if emu.quirks.jump_offset_uses_vx {
+                        let x = ((n >> 8) & 0xF) as usize;
+                        emu.reg[x] as u16
+                    } else {
+                        emu.reg[0] as u16
+                    };
+                    emu.pc = offset + n;
                 }
                 _ => println!("ERROR: Unknown operand for opcode: {:x}", instr.opcode)
             }
@@ -429,7 +903,7 @@ fn execute(emu: &mut Emulator, instr: Instruction) {
         0xC => {
             match (instr.op1, instr.op2) {
                 (Some(x), Some(n) ) => {
-                    emu.reg[x as usize] = (n as u8) & ((rand() & 0xff) as u8);
+                    emu.reg[x as usize] = (n as u8) & emu.next_rand();
                 }
                 _ => println!("ERROR: Unknown operand for opcode: {:x}", instr.opcode)
             }
@@ -442,19 +916,28 @@ fn execute(emu: &mut Emulator, instr: Instruction) {
                     for i in 0..n {
                         sprite[i as usize] = emu.getWord(emu.idx + (i as u16));
                     }
-                    
+
                     let Vx = emu.reg[x as usize] as u16;
                     let Vy = emu.reg[y as usize] as u16;
-                    
-                    let mut px = Vx + (Vy * 32);
-                
+
                     let mut VF = 0;
-                    for s in 0..n {
+                    for s in 0..(n as u16) {
+                        let row = Vy + s;
+                        if emu.quirks.clip_sprites && row >= 32 {
+                            break;
+                        }
+                        let row = row % 32;
+
                         for o in 0..8 {
-                            if emu.writePixel(px + o, (sprite[s as usize] >> (7 - o)) & 0x1) == 1 {VF = 1;}
+                            let col = Vx + o;
+                            if emu.quirks.clip_sprites && col >= 64 {
+                                continue;
+                            }
+                            let col = col % 64;
+
+                            let px = col + (row * 64);
+                            if emu.writePixel(px, (sprite[s as usize] >> (7 - o)) & 0x1) == 1 {VF = 1;}
                         }
-                        px += 64;
-                        if px >= TOTAL_PIXELS {break;}
                     }
                     emu.reg[0xF] = VF;
                 }
@@ -521,27 +1004,32 @@ fn execute(emu: &mut Emulator, instr: Instruction) {
                 }
                 (Some(x), Some(0x33)) => {
                     let x = x as usize;
-                    let idx = emu.idx as usize;
-                    emu.mem[idx] = (emu.reg[x] / 100) % 10;
-                    emu.mem[idx + 1] = (emu.reg[x] / 10) % 10;
-                    emu.mem[idx + 2] = emu.reg[x] % 10;
+                    let idx = emu.idx;
+                    emu.putWord(idx, (emu.reg[x] / 100) % 10);
+                    emu.putWord(idx + 1, (emu.reg[x] / 10) % 10);
+                    emu.putWord(idx + 2, emu.reg[x] % 10);
                 }
                 (Some(x), Some(0x55)) => {
-                    let mut idx = emu.idx as usize;
-                    for i in 0..x {
+                    let mut idx = emu.idx;
+                    for i in 0..=x {
                         let i = i as usize;
-                        emu.mem[idx] = emu.reg[i];
+                        emu.putWord(idx, emu.reg[i]);
                         idx += 1;
                     }
-
+                    if emu.quirks.load_store_increments_idx {
+                        emu.idx = emu.idx.wrapping_add(x + 1);
+                    }
                 }
                 (Some(x), Some(0x65)) => {
-                    let mut idx = emu.idx as usize;
-                    for i in 0..x {
+                    let mut idx = emu.idx;
+                    for i in 0..=x {
                         let i = i as usize;
-                        emu.reg[i] = emu.mem[idx];
+                        emu.reg[i] = emu.getWord(idx);
                         idx += 1;
                     }
+                    if emu.quirks.load_store_increments_idx {
+                        emu.idx = emu.idx.wrapping_add(x + 1);
+                    }
                 }
                 _ => println!("ERROR: Unknown operand for opcode: {:x}", instr.opcode)
             }
@@ -551,9 +1039,33 @@ fn execute(emu: &mut Emulator, instr: Instruction) {
 }
 
 
+fn parse_flag_arg(name: &str) -> Option<String> {
+    let args: Vec<String> = env::args().collect();
+    let mut i = 1;
+    while i < args.len() {
+        if args[i] == name {
+            return args.get(i + 1).cloned();
+        }
+        i += 1;
+    }
+    return None;
+}
+
 #[macroquad::main("Chip8")]
 async fn main() {
-    let mut emu = Emulator::init();
+    let seed = parse_flag_arg("--seed").and_then(|s| s.parse().ok());
+    let quirks = match parse_flag_arg("--variant") {
+        Some(name) => match Quirks::from_name(&name) {
+            Some(quirks) => quirks,
+            None => {
+                println!("ERROR: Unknown variant: {0}", name);
+                Quirks::default()
+            }
+        }
+        None => Quirks::default(),
+    };
+
+    let mut emu = Emulator::init_with_quirks(quirks, seed);
     let delay = Arc::clone(&emu.delay);
     let sound = Arc::clone(&emu.sound);
 
@@ -561,11 +1073,43 @@ async fn main() {
 
     emu.loadROM("./roms/IBM Logo.ch8");
 
+    let mut debugger = Debugger::new();
+
     loop {
-        let raw = fetch(&mut emu);
-        let instr = decode(raw);
-        execute(&mut emu, instr);
-        
+        for _ in 0..emu.quirks.cycles_per_frame {
+            if debugger.trace_only {
+                let raw = emu.getDWord(emu.pc);
+                println!("{:04X}: {:04X}  {}", emu.pc, raw, Instruction::new(raw));
+            }
+
+            if debugger.should_break(emu.pc) {
+                loop {
+                    print!("(chip8db) ");
+                    io::stdout().flush().unwrap();
+
+                    let mut line = String::new();
+                    io::stdin().lock().read_line(&mut line).unwrap();
+                    let line = line.trim();
+
+                    let cmd = if line.is_empty() {
+                        debugger.last_command.clone().unwrap_or_default()
+                    } else {
+                        line.to_string()
+                    };
+
+                    let resume = run_debugger_command(&mut debugger, &emu, &cmd);
+                    debugger.last_command = Some(cmd);
+                    if resume {
+                        break;
+                    }
+                }
+            } else if debugger.repeat > 0 {
+                debugger.repeat -= 1;
+            }
+
+            execute_block(&mut emu);
+        }
+
         emu.draw();
         next_frame().await;
     }